@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{anyhow, Result};
+use crates_index::{Dependency, DependencyKind, Version};
+use serde::Serialize;
+
+use crate::registry;
+
+fn kind_name(kind: DependencyKind) -> &'static str {
+    match kind {
+        DependencyKind::Normal => "normal",
+        DependencyKind::Build => "build",
+        DependencyKind::Dev => "dev",
+    }
+}
+
+fn kind_rank(kind: DependencyKind) -> u8 {
+    match kind {
+        DependencyKind::Normal => 0,
+        DependencyKind::Build => 1,
+        DependencyKind::Dev => 2,
+    }
+}
+
+/// Dependencies of the same name can appear more than once per kind (e.g. one entry per
+/// `target` cfg), so group on name *and* target rather than just name.
+type DepKey<'a> = (u8, &'a str, Option<&'a str>);
+
+fn index_deps(version: &Version) -> BTreeMap<DepKey<'_>, &Dependency> {
+    version
+        .dependencies()
+        .iter()
+        .map(|d| ((kind_rank(d.kind()), d.name(), d.target()), d))
+        .collect()
+}
+
+/// A dependency as it appears on one side of a diff.
+#[derive(Serialize)]
+pub struct DepSummary {
+    pub name: String,
+    pub target: Option<String>,
+    pub requirement: String,
+}
+
+fn dep_summary(name: &str, target: Option<&str>, dep: &Dependency) -> DepSummary {
+    DepSummary {
+        name: name.to_string(),
+        target: target.map(str::to_string),
+        requirement: dep.requirement().to_string(),
+    }
+}
+
+/// A dependency present on both sides with at least one field that changed. Fields that didn't
+/// change are `None` rather than `Some((x, x))`.
+#[derive(Serialize)]
+pub struct ChangedDep {
+    pub name: String,
+    pub target: Option<String>,
+    pub old_requirement: String,
+    pub new_requirement: String,
+    pub optional: Option<(bool, bool)>,
+    pub default_features: Option<(bool, bool)>,
+    pub features: Option<(Vec<String>, Vec<String>)>,
+}
+
+fn changed_dep(name: &str, target: Option<&str>, old: &Dependency, new: &Dependency) -> ChangedDep {
+    ChangedDep {
+        name: name.to_string(),
+        target: target.map(str::to_string),
+        old_requirement: old.requirement().to_string(),
+        new_requirement: new.requirement().to_string(),
+        optional: (old.is_optional() != new.is_optional()).then_some((old.is_optional(), new.is_optional())),
+        default_features: (old.has_default_features() != new.has_default_features())
+            .then_some((old.has_default_features(), new.has_default_features())),
+        features: (old.features() != new.features())
+            .then_some((old.features().to_vec(), new.features().to_vec())),
+    }
+}
+
+/// Added/removed/changed dependencies of a single kind (normal, build, or dev).
+#[derive(Serialize, Default)]
+pub struct KindDiff {
+    pub added: Vec<DepSummary>,
+    pub removed: Vec<DepSummary>,
+    pub changed: Vec<ChangedDep>,
+}
+
+fn kind_diff(deps1: &BTreeMap<DepKey, &Dependency>, deps2: &BTreeMap<DepKey, &Dependency>, rank: u8) -> KindDiff {
+    let mut diff = KindDiff::default();
+    let keys = deps1
+        .keys()
+        .chain(deps2.keys())
+        .filter(|(r, ..)| *r == rank)
+        .copied()
+        .collect::<BTreeSet<_>>();
+    for key @ (_, name, target) in keys {
+        match (deps1.get(&key), deps2.get(&key)) {
+            (None, Some(dep)) => diff.added.push(dep_summary(name, target, dep)),
+            (Some(dep), None) => diff.removed.push(dep_summary(name, target, dep)),
+            (Some(old), Some(new)) => {
+                if old != new {
+                    diff.changed.push(changed_dep(name, target, old, new));
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+    diff
+}
+
+fn target_suffix(target: &Option<String>) -> String {
+    target.as_ref().map(|t| format!(" (target = {t:?})")).unwrap_or_default()
+}
+
+/// Structured result of [`deps_diff`], serializable for `--format json`.
+#[derive(Serialize)]
+pub struct DepsDiff {
+    pub normal: KindDiff,
+    pub build: KindDiff,
+    pub dev: KindDiff,
+}
+
+impl DepsDiff {
+    pub fn to_text(&self) -> String {
+        let mut output = String::new();
+        for (kind, diff) in [
+            (DependencyKind::Normal, &self.normal),
+            (DependencyKind::Build, &self.build),
+            (DependencyKind::Dev, &self.dev),
+        ] {
+            if diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty() {
+                continue;
+            }
+            output.push_str(&format!("{} dependencies:\n", kind_name(kind)));
+            for d in &diff.added {
+                output.push_str(&format!("+ {}{} = {:?}\n", d.name, target_suffix(&d.target), d.requirement));
+            }
+            for d in &diff.removed {
+                output.push_str(&format!("- {}{} = {:?}\n", d.name, target_suffix(&d.target), d.requirement));
+            }
+            for c in &diff.changed {
+                output.push_str(&format!("~ {}{}\n", c.name, target_suffix(&c.target)));
+                if c.old_requirement != c.new_requirement {
+                    output.push_str(&format!("    req: {:?} -> {:?}\n", c.old_requirement, c.new_requirement));
+                }
+                if let Some((old, new)) = c.optional {
+                    output.push_str(&format!("    optional: {old} -> {new}\n"));
+                }
+                if let Some((old, new)) = c.default_features {
+                    output.push_str(&format!("    default_features: {old} -> {new}\n"));
+                }
+                if let Some((old, new)) = &c.features {
+                    output.push_str(&format!("    features: {old:?} -> {new:?}\n"));
+                }
+            }
+        }
+        output
+    }
+}
+
+pub fn deps_diff(index: &registry::Index, crate_name: &str, v1: &str, v2: &str) -> Result<DepsDiff> {
+    let krate = index.crate_(crate_name)?;
+    let v1 = krate
+        .versions()
+        .iter()
+        .find(|v| v.version() == v1)
+        .ok_or_else(|| anyhow!("Couldn't find version {} for crate {}", v1, crate_name))?;
+    let v2 = krate
+        .versions()
+        .iter()
+        .find(|v| v.version() == v2)
+        .ok_or_else(|| anyhow!("Couldn't find version {} for crate {}", v2, crate_name))?;
+
+    let deps1 = index_deps(v1);
+    let deps2 = index_deps(v2);
+    Ok(DepsDiff {
+        normal: kind_diff(&deps1, &deps2, kind_rank(DependencyKind::Normal)),
+        build: kind_diff(&deps1, &deps2, kind_rank(DependencyKind::Build)),
+        dev: kind_diff(&deps1, &deps2, kind_rank(DependencyKind::Dev)),
+    })
+}
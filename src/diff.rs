@@ -0,0 +1,263 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::Serialize;
+use similar::{capture_diff_slices, group_diff_ops, Algorithm, DiffOp};
+
+/// Matches a single path component against a simple shell-style glob (`*` and `?` only), the
+/// same subset GNU `diff -x` supports for its exclude patterns.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    fn inner(pattern: &[u8], text: &[u8]) -> bool {
+        match (pattern.first(), text.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => inner(&pattern[1..], text) || (!text.is_empty() && inner(pattern, &text[1..])),
+            (Some(b'?'), Some(_)) => inner(&pattern[1..], &text[1..]),
+            (Some(p), Some(t)) if p == t => inner(&pattern[1..], &text[1..]),
+            _ => false,
+        }
+    }
+    inner(pattern.as_bytes(), text.as_bytes())
+}
+
+fn is_ignored(relative: &Path, ignore: &[String]) -> bool {
+    relative.components().any(|c| {
+        let name = c.as_os_str().to_string_lossy();
+        ignore.iter().any(|pattern| glob_match(pattern, &name))
+    })
+}
+
+/// Recursively lists every plain file under `root`, as paths relative to it. Symlinks are
+/// skipped; `fetch_and_extract` never creates any, but a caller-supplied directory might.
+fn collect_files(root: &Path) -> Result<BTreeSet<PathBuf>> {
+    let mut files = BTreeSet::new();
+    let mut stack = vec![PathBuf::new()];
+    while let Some(relative) = stack.pop() {
+        for entry in std::fs::read_dir(root.join(&relative))? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let child = relative.join(entry.file_name());
+            if file_type.is_dir() {
+                stack.push(child);
+            } else if file_type.is_file() {
+                files.insert(child);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Splits text into lines the way `similar::TextDiff::from_lines` does: each line keeps its
+/// trailing `\n` attached, so joining the pieces back together round-trips the original text.
+fn split_lines(text: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut rest = text;
+    while let Some(idx) = rest.find('\n') {
+        lines.push(&rest[..=idx]);
+        rest = &rest[idx + 1..];
+    }
+    if !rest.is_empty() {
+        lines.push(rest);
+    }
+    lines
+}
+
+/// Normalizes a line for whitespace-insensitive comparison, mirroring GNU `diff`'s `-w`
+/// (`--ignore-all-space`): every whitespace character is dropped, not just leading/trailing runs.
+fn normalize_whitespace(line: &str) -> String {
+    line.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Formats a hunk's line range the way `similar`'s unified diff does: a single line is just its
+/// number, an empty range is the line before it followed by `,0`, anything else is `start,len`.
+fn hunk_range(start: usize, end: usize) -> String {
+    let mut beginning = start + 1;
+    let len = end.saturating_sub(start);
+    if len == 1 {
+        return beginning.to_string();
+    }
+    if len == 0 {
+        beginning -= 1;
+    }
+    format!("{beginning},{len}")
+}
+
+/// Builds a unified diff the same way this command always has (`diff -urw`/`diff -w`): lines
+/// that only differ in whitespace count as equal, so a reindent or trailing-whitespace cleanup
+/// doesn't show up as a change. Returns `None` if the two texts are equal under that rule.
+fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> Option<String> {
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+    let old_normalized = old_lines.iter().map(|line| normalize_whitespace(line)).collect::<Vec<_>>();
+    let new_normalized = new_lines.iter().map(|line| normalize_whitespace(line)).collect::<Vec<_>>();
+
+    let ops = capture_diff_slices(Algorithm::Myers, &old_normalized, &new_normalized);
+    let groups = group_diff_ops(ops, 3);
+    if groups.is_empty() {
+        return None;
+    }
+
+    let mut output = format!("--- {old_label}\n+++ {new_label}\n");
+    for group in &groups {
+        let old_start = group[0].old_range().start;
+        let new_start = group[0].new_range().start;
+        let old_end = group[group.len() - 1].old_range().end;
+        let new_end = group[group.len() - 1].new_range().end;
+        output.push_str(&format!("@@ -{} +{} @@\n", hunk_range(old_start, old_end), hunk_range(new_start, new_end)));
+        for op in group {
+            match *op {
+                DiffOp::Equal { old_index, len, .. } => {
+                    for line in &old_lines[old_index..old_index + len] {
+                        output.push_str(&format!(" {line}"));
+                    }
+                }
+                DiffOp::Delete { old_index, old_len, .. } => {
+                    for line in &old_lines[old_index..old_index + old_len] {
+                        output.push_str(&format!("-{line}"));
+                    }
+                }
+                DiffOp::Insert { new_index, new_len, .. } => {
+                    for line in &new_lines[new_index..new_index + new_len] {
+                        output.push_str(&format!("+{line}"));
+                    }
+                }
+                DiffOp::Replace { old_index, old_len, new_index, new_len } => {
+                    for line in &old_lines[old_index..old_index + old_len] {
+                        output.push_str(&format!("-{line}"));
+                    }
+                    for line in &new_lines[new_index..new_index + new_len] {
+                        output.push_str(&format!("+{line}"));
+                    }
+                }
+            }
+        }
+    }
+    Some(output)
+}
+
+fn color_unified_diff(diff: &str) -> String {
+    diff.lines()
+        .map(|line| {
+            if line.starts_with('+') && !line.starts_with("+++") {
+                format!("\x1b[32m{line}\x1b[0m")
+            } else if line.starts_with('-') && !line.starts_with("---") {
+                format!("\x1b[31m{line}\x1b[0m")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A file that exists on both sides but differs. `diff` is the plain (uncolored) unified diff
+/// text, or a `Binary files ... differ` notice if either side isn't valid UTF-8.
+#[derive(Serialize)]
+pub struct ChangedFile {
+    pub path: String,
+    pub diff: String,
+}
+
+/// Structured result of [`diff_sources`], serializable for `--format json`.
+#[derive(Serialize)]
+pub struct SourceDiff {
+    pub only_in_v1: Vec<String>,
+    pub only_in_v2: Vec<String>,
+    pub changed: Vec<ChangedFile>,
+}
+
+impl SourceDiff {
+    /// Renders the same human-readable, colorized output this command has always produced.
+    pub fn to_text(&self, dir1: &Path, dir2: &Path) -> String {
+        let mut output = String::new();
+        for path in &self.only_in_v1 {
+            output.push_str(&format!("Only in {}: {path}\n", dir1.display()));
+        }
+        for path in &self.only_in_v2 {
+            output.push_str(&format!("Only in {}: {path}\n", dir2.display()));
+        }
+        for file in &self.changed {
+            output.push_str(&color_unified_diff(&file.diff));
+            output.push('\n');
+        }
+        output
+    }
+}
+
+/// Diffs two extracted source trees in-process with `similar`, instead of shelling out to GNU
+/// `diff` (which isn't portable to Windows). `ignore` is a list of glob patterns matched
+/// against path components, playing the role of `diff`'s old hardcoded `-x` exclusions.
+pub fn diff_sources(dir1: &Path, dir2: &Path, ignore: &[String]) -> Result<SourceDiff> {
+    let files1 = collect_files(dir1)?;
+    let files2 = collect_files(dir2)?;
+    let mut result = SourceDiff { only_in_v1: Vec::new(), only_in_v2: Vec::new(), changed: Vec::new() };
+
+    for relative in files1.union(&files2) {
+        if is_ignored(relative, ignore) {
+            continue;
+        }
+        let path1 = dir1.join(relative);
+        let path2 = dir2.join(relative);
+        match (path1.exists(), path2.exists()) {
+            (true, false) => result.only_in_v1.push(relative.display().to_string()),
+            (false, true) => result.only_in_v2.push(relative.display().to_string()),
+            (true, true) => {
+                let old = std::fs::read(&path1)?;
+                let new = std::fs::read(&path2)?;
+                let path = relative.display().to_string();
+                match (std::str::from_utf8(&old), std::str::from_utf8(&new)) {
+                    (Ok(old), Ok(new)) => {
+                        if let Some(diff) =
+                            unified_diff(old, new, &path1.display().to_string(), &path2.display().to_string())
+                        {
+                            result.changed.push(ChangedFile { path, diff });
+                        }
+                    }
+                    _ => {
+                        if old != new {
+                            result.changed.push(ChangedFile {
+                                path,
+                                diff: format!("Binary files {} and {} differ", path1.display(), path2.display()),
+                            });
+                        }
+                    }
+                }
+            }
+            (false, false) => unreachable!(),
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unified_diff_ignores_whitespace_only_changes() {
+        let old = "fn main() {\n\tlet x = 1;\n}\n";
+        let new = "fn main() {\n    let x = 1;   \n}\n";
+        assert_eq!(unified_diff(old, new, "old", "new"), None);
+    }
+
+    #[test]
+    fn unified_diff_reports_a_real_change_around_unchanged_whitespace_noise() {
+        let old = "fn main() {\n\tlet x = 1;\n}\n";
+        let new = "fn main() {\n    let x = 2;   \n}\n";
+        let diff = unified_diff(old, new, "old", "new").unwrap();
+        assert!(diff.contains("-\tlet x = 1;\n"), "{diff}");
+        assert!(diff.contains("+    let x = 2;   \n"), "{diff}");
+    }
+
+    #[test]
+    fn diff_sources_skips_files_that_only_differ_in_whitespace() {
+        let dir1 = tempfile::tempdir().unwrap();
+        let dir2 = tempfile::tempdir().unwrap();
+        std::fs::write(dir1.path().join("lib.rs"), "fn main() {\n\tlet x = 1;\n}\n").unwrap();
+        std::fs::write(dir2.path().join("lib.rs"), "fn main() {\n    let x = 1;   \n}\n").unwrap();
+
+        let diff = diff_sources(dir1.path(), dir2.path(), &[]).unwrap();
+
+        assert!(diff.changed.is_empty());
+    }
+}
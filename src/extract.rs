@@ -0,0 +1,154 @@
+use std::fs::File;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, bail, Result};
+use flate2::read::GzDecoder;
+use tar::Archive;
+
+use crate::registry;
+
+/// Refuse to extract a tarball that decompresses to more than this; guards against a crafted
+/// `.crate` file that's a zip-bomb.
+const MAX_EXTRACTED_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Downloads `name@version` and extracts it under `dest_dir`, applying the same hardening
+/// crates.rs applies to untrusted `.crate` files: no absolute paths or `..` traversal, no
+/// symlinks/hardlinks, and a ceiling on total decompressed size. `tar`/`flate2` do the
+/// unpacking in-process, so this works without a `tar` binary on `$PATH`.
+///
+/// Crate tarballs wrap everything in a `{name}-{version}/` directory; that prefix is stripped,
+/// so the returned path's contents are the plain source tree.
+pub fn fetch_and_extract(index: &registry::Index, name: &str, version: &str, dest_dir: &Path) -> Result<PathBuf> {
+    let url = index.download_url(name, version)?;
+    let bytes = reqwest::blocking::get(url)?.bytes()?;
+    extract_archive(&bytes, name, version, dest_dir, MAX_EXTRACTED_BYTES)
+}
+
+/// The hardened extraction loop itself, split out from `fetch_and_extract` so it can be unit
+/// tested against an in-memory tarball instead of a network download. `max_bytes` is always
+/// `MAX_EXTRACTED_BYTES` outside tests.
+fn extract_archive(bytes: &[u8], name: &str, version: &str, dest_dir: &Path, max_bytes: u64) -> Result<PathBuf> {
+    let out_dir = dest_dir.join(format!("{}-{}", name, version));
+    std::fs::create_dir_all(&out_dir)?;
+
+    let mut archive = Archive::new(GzDecoder::new(bytes));
+    let mut total_bytes = 0u64;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            continue;
+        }
+        if !entry_type.is_file() && !entry_type.is_dir() {
+            continue;
+        }
+
+        let path = entry.path()?.into_owned();
+        if path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+            return Err(anyhow!("refusing to extract unsafe path in {name}-{version}.crate: {}", path.display()));
+        }
+        // Drop the leading `{name}-{version}/` component the tarball wraps everything in.
+        let relative = path.components().skip(1).collect::<PathBuf>();
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+
+        total_bytes += entry.size();
+        if total_bytes > max_bytes {
+            bail!("{name}-{version}.crate exceeds the {max_bytes} byte extraction ceiling");
+        }
+
+        let dest = out_dir.join(&relative);
+        if entry_type.is_dir() {
+            std::fs::create_dir_all(&dest)?;
+        } else {
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut file = File::create(&dest)?;
+            io::copy(&mut entry, &mut file)?;
+        }
+    }
+    Ok(out_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tar::{Builder, EntryType, Header};
+
+    use super::*;
+
+    /// Builds a tarball with the given entries. Writes the path straight into the raw header
+    /// bytes rather than going through `Header::set_path`, which validates away the exact
+    /// `../` traversal and absolute paths these tests need to construct to prove
+    /// `extract_archive` rejects them itself.
+    fn tarball(entries: &[(&str, EntryType, &[u8], Option<&str>)]) -> Vec<u8> {
+        let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, entry_type, data, link_name) in entries {
+            let mut header = Header::new_gnu();
+            let gnu = header.as_gnu_mut().unwrap();
+            let name = path.as_bytes();
+            gnu.name[..name.len()].copy_from_slice(name);
+            if let Some(link_name) = link_name {
+                let link_name = link_name.as_bytes();
+                gnu.linkname[..link_name.len()].copy_from_slice(link_name);
+            }
+            header.set_entry_type(*entry_type);
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, *data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn extracts_files_and_strips_the_name_version_prefix() {
+        let bytes = tarball(&[("pkg-1.0.0/src/lib.rs", EntryType::Regular, b"fn main() {}", None)]);
+        let dest = tempfile::tempdir().unwrap();
+
+        let out_dir = extract_archive(&bytes, "pkg", "1.0.0", dest.path(), MAX_EXTRACTED_BYTES).unwrap();
+
+        assert_eq!(std::fs::read_to_string(out_dir.join("src/lib.rs")).unwrap(), "fn main() {}");
+    }
+
+    #[test]
+    fn rejects_parent_dir_traversal() {
+        let bytes = tarball(&[("pkg-1.0.0/../../evil", EntryType::Regular, b"x", None)]);
+        let dest = tempfile::tempdir().unwrap();
+
+        assert!(extract_archive(&bytes, "pkg", "1.0.0", dest.path(), MAX_EXTRACTED_BYTES).is_err());
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        let bytes = tarball(&[("/etc/passwd", EntryType::Regular, b"x", None)]);
+        let dest = tempfile::tempdir().unwrap();
+
+        assert!(extract_archive(&bytes, "pkg", "1.0.0", dest.path(), MAX_EXTRACTED_BYTES).is_err());
+    }
+
+    #[test]
+    fn skips_symlinks_instead_of_following_them() {
+        let bytes = tarball(&[
+            ("pkg-1.0.0/src/lib.rs", EntryType::Regular, b"fn main() {}", None),
+            ("pkg-1.0.0/evil-link", EntryType::Symlink, b"", Some("/etc/passwd")),
+        ]);
+        let dest = tempfile::tempdir().unwrap();
+
+        let out_dir = extract_archive(&bytes, "pkg", "1.0.0", dest.path(), MAX_EXTRACTED_BYTES).unwrap();
+
+        assert!(!out_dir.join("evil-link").exists());
+    }
+
+    #[test]
+    fn enforces_the_extracted_size_ceiling() {
+        let bytes = tarball(&[("pkg-1.0.0/big.bin", EntryType::Regular, &[0u8; 1024], None)]);
+        let dest = tempfile::tempdir().unwrap();
+
+        assert!(extract_archive(&bytes, "pkg", "1.0.0", dest.path(), 10).is_err());
+    }
+}
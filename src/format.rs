@@ -0,0 +1,8 @@
+/// Output format shared by the commands that support `--format json` for scripting/CI use.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[clap(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+}
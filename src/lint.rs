@@ -0,0 +1,146 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::process::Command;
+use std::str::from_utf8;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{extract, registry};
+
+/// A single clippy warning/error, keyed by lint name + message + the file/line range it was
+/// reported at. Line numbers can drift between versions, so this is a best-effort match, not a
+/// guarantee that two diagnostics with the same key are "the same bug".
+#[derive(Serialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Diagnostic {
+    pub lint: String,
+    pub message: String,
+    pub file: String,
+    pub line_start: usize,
+    pub line_end: usize,
+}
+
+/// Strips the `{name}-{version}/` prefix crate tarballs wrap everything in, so diagnostics from
+/// two different extracted versions line up on the same relative path. `cargo clippy` is run
+/// with `dir` as its working directory, so paths are usually already relative to it; this also
+/// handles the case where a path comes back absolute.
+fn normalize_path(file_name: &str, dir: &Path) -> String {
+    let path = Path::new(file_name);
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+    match dir.file_name().and_then(|n| n.to_str()) {
+        Some(dir_name) => relative.strip_prefix(dir_name).unwrap_or(relative),
+        None => relative,
+    }
+    .display()
+    .to_string()
+}
+
+/// Runs `cargo clippy --message-format=json` against the crate extracted at `dir` and collects
+/// every warning/error it reports on the crate's own code into a set of [`Diagnostic`]s.
+fn run_clippy(dir: &Path) -> Result<BTreeSet<Diagnostic>> {
+    let mut cmd = Command::new("cargo");
+    cmd.args(["clippy", "--message-format=json", "--quiet"]).current_dir(dir);
+    // Not `success_or_error`: clippy's process exit code alone can't distinguish "found
+    // warnings" (normal, non-zero) from "the crate failed to build" (a real failure we need to
+    // surface); the `build-finished` message on stdout is the one source of truth for that.
+    let output = cmd.output()?;
+
+    let mut diagnostics = BTreeSet::new();
+    let mut build_succeeded = None;
+    for line in from_utf8(&output.stdout)?.lines() {
+        let Ok(msg) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        match msg["reason"].as_str() {
+            Some("build-finished") => build_succeeded = msg["success"].as_bool(),
+            Some("compiler-message") => {
+                let message = &msg["message"];
+                let level = message["level"].as_str().unwrap_or_default();
+                if level != "warning" && level != "error" {
+                    continue;
+                }
+                let Some(span) = message["spans"]
+                    .as_array()
+                    .and_then(|spans| spans.iter().find(|s| s["is_primary"].as_bool() == Some(true)))
+                else {
+                    continue;
+                };
+                diagnostics.insert(Diagnostic {
+                    lint: message["code"]["code"].as_str().unwrap_or("unknown").to_string(),
+                    message: message["message"].as_str().unwrap_or_default().to_string(),
+                    file: normalize_path(span["file_name"].as_str().unwrap_or_default(), dir),
+                    line_start: span["line_start"].as_u64().unwrap_or_default() as usize,
+                    line_end: span["line_end"].as_u64().unwrap_or_default() as usize,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    if build_succeeded != Some(true) {
+        return Err(anyhow!(
+            "cargo clippy failed to build {}, so its diagnostics can't be compared.\nstderr:\n{}",
+            dir.display(),
+            from_utf8(&output.stderr)?
+        ));
+    }
+    Ok(diagnostics)
+}
+
+/// Structured result of [`lint_diff`], serializable for `--format json`.
+#[derive(Serialize)]
+pub struct LintDiffReport {
+    pub introduced: Vec<Diagnostic>,
+    pub fixed: Vec<Diagnostic>,
+    /// Net change in diagnostic count per lint name (`introduced` minus `fixed`), e.g. `12` for
+    /// `clippy::needless_borrow` meaning 12 more instances than before.
+    pub lint_counts: BTreeMap<String, i64>,
+}
+
+impl LintDiffReport {
+    pub fn to_text(&self) -> String {
+        let mut output = String::new();
+        for d in &self.introduced {
+            output.push_str(&format!("+ {}:{}: {}: {}\n", d.file, d.line_start, d.lint, d.message));
+        }
+        for d in &self.fixed {
+            output.push_str(&format!("- {}:{}: {}: {}\n", d.file, d.line_start, d.lint, d.message));
+        }
+        if !self.lint_counts.is_empty() {
+            let summary = self
+                .lint_counts
+                .iter()
+                .map(|(lint, count)| format!("{}{count} {lint}", if *count > 0 { "+" } else { "" }))
+                .collect::<Vec<_>>()
+                .join(", ");
+            output.push_str(&format!("\n{summary}\n"));
+        }
+        output
+    }
+}
+
+/// Downloads and extracts both versions, runs `cargo clippy` against each, and reports which
+/// warnings/errors were introduced or fixed between them.
+pub fn lint_diff(index: &registry::Index, crate_name: &str, v1: &str, v2: &str) -> Result<LintDiffReport> {
+    let dir = tempfile::tempdir()?;
+    let dir1 = extract::fetch_and_extract(index, crate_name, v1, dir.path())?;
+    let dir2 = extract::fetch_and_extract(index, crate_name, v2, dir.path())?;
+    let diags1 = run_clippy(&dir1)?;
+    let diags2 = run_clippy(&dir2)?;
+    dir.close()?;
+
+    let introduced = diags2.difference(&diags1).cloned().collect::<Vec<_>>();
+    let fixed = diags1.difference(&diags2).cloned().collect::<Vec<_>>();
+
+    let mut lint_counts = BTreeMap::<String, i64>::new();
+    for d in &introduced {
+        *lint_counts.entry(d.lint.clone()).or_default() += 1;
+    }
+    for d in &fixed {
+        *lint_counts.entry(d.lint.clone()).or_default() -= 1;
+    }
+    lint_counts.retain(|_, count| *count != 0);
+
+    Ok(LintDiffReport { introduced, fixed, lint_counts })
+}
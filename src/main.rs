@@ -1,16 +1,31 @@
-use std::collections::{BTreeMap, BTreeSet, HashSet};
-use std::path::Path;
 use std::process::{Command, Output};
 use std::str::from_utf8;
 
 use anyhow::{anyhow, Result};
 use clap::{Parser, Subcommand};
-use reqwest;
 use tempfile;
 
+use format::Format;
+
+mod deps;
+mod diff;
+mod extract;
+mod format;
+mod lint;
+mod registry;
+mod semver;
+mod tree;
+
 #[derive(Parser)]
 struct Cli {
     crate_name: String,
+    /// Registry to query: a name from `[registries]` in cargo config, or a bare
+    /// `https://...`/`sparse+https://...` index URL. Defaults to crates.io.
+    #[arg(long, global = true)]
+    registry: Option<String>,
+    /// Output format. `json` is meant for scripting/CI; every subcommand supports it.
+    #[arg(long, value_enum, default_value_t = Format::Text, global = true)]
+    format: Format,
     #[command(subcommand)]
     command: Cmd,
 }
@@ -18,11 +33,26 @@ struct Cli {
 #[derive(Subcommand)]
 enum Cmd {
     Versions,
-    Diff { v1: String, v2: String },
+    Diff {
+        v1: String,
+        v2: String,
+        /// Glob pattern (matched against path components) to exclude from the diff. Repeatable.
+        #[arg(long, default_values = ["ci.yml", ".cargo_vcs_info.json"])]
+        ignore: Vec<String>,
+    },
     Deps { v1: String, v2: String },
+    /// Resolve the full transitive dependency graph for each version and diff the two.
+    TreeDiff { v1: String, v2: String },
+    /// Classify the semver bump between two versions per Cargo's caret rules, and flag
+    /// dependency changes that look inconsistent with it (e.g. a removed dependency on a
+    /// patch release). Intended for gating a release in CI.
+    Semver { v1: String, v2: String },
+    /// Run clippy against both versions' extracted sources and report which warnings/errors
+    /// were introduced or fixed, so a dependency upgrade's code-quality impact is visible.
+    LintDiff { v1: String, v2: String },
 }
 
-fn success_or_error(cmd: &mut Command) -> Result<Output> {
+pub(crate) fn success_or_error(cmd: &mut Command) -> Result<Output> {
     let output = cmd.output()?;
     if !output.status.success() {
         return Err(anyhow!(
@@ -35,158 +65,71 @@ fn success_or_error(cmd: &mut Command) -> Result<Output> {
     Ok(output)
 }
 
-fn fetch_crate(name: &str, version: &str, dir: &impl AsRef<Path>) -> Result<()> {
-    let path = dir.as_ref().join(format!("{}-{}.tar.gz", name, version));
-    std::fs::write(
-        &path,
-        reqwest::blocking::get(format!(
-            "https://crates.io/api/v1/crates/{}/{}/download",
-            name, version
-        ))?
-        .bytes()?,
-    )?;
-    success_or_error(Command::new("tar").arg("xf").arg(&path).current_dir(&dir))?;
-    Ok(())
-}
-
 fn main() -> Result<()> {
     let args = Cli::parse();
+    let registry_url = registry::resolve_registry_url(args.registry.as_deref())?;
     match args.command {
         Cmd::Versions => {
-            let index = crates_index::GitIndex::new_cargo_default()?;
-            let krate = index
-                .crate_(&args.crate_name)
-                .ok_or(anyhow!("Couldn't find crate name {}", args.crate_name))?;
-            for v in krate.versions().iter().filter(|v| !v.is_yanked()) {
-                println!("{}", v.version())
+            let index = registry::Index::open(&registry_url)?;
+            let krate = index.crate_(&args.crate_name)?;
+            let versions = krate
+                .versions()
+                .iter()
+                .filter(|v| !v.is_yanked())
+                .map(|v| v.version())
+                .collect::<Vec<_>>();
+            match args.format {
+                Format::Text => {
+                    for v in &versions {
+                        println!("{v}")
+                    }
+                }
+                Format::Json => println!("{}", serde_json::to_string_pretty(&versions)?),
             }
         }
-        Cmd::Diff { v1, v2 } => {
+        Cmd::Diff { v1, v2, ignore } => {
+            let index = registry::Index::open(&registry_url)?;
             let dir = tempfile::tempdir()?;
-            fetch_crate(&args.crate_name, &v1, &dir.path())?;
-            fetch_crate(&args.crate_name, &v2, &dir.path())?;
-
-            let diff = Command::new("diff")
-                .args([
-                    "-urw",
-                    "--color=always",
-                    "-x",
-                    "ci.yml",
-                    "-x",
-                    ".cargo_vcs_info.json",
-                ])
-                .args([
-                    format!("{}-{}", args.crate_name, v1),
-                    format!("{}-{}", args.crate_name, v2),
-                ])
-                .current_dir(&dir)
-                .output()?;
-            println!("{}", from_utf8(&diff.stdout)?);
+            let dir1 = extract::fetch_and_extract(&index, &args.crate_name, &v1, dir.path())?;
+            let dir2 = extract::fetch_and_extract(&index, &args.crate_name, &v2, dir.path())?;
+            let source_diff = diff::diff_sources(&dir1, &dir2, &ignore)?;
+            match args.format {
+                Format::Text => print!("{}", source_diff.to_text(&dir1, &dir2)),
+                Format::Json => println!("{}", serde_json::to_string_pretty(&source_diff)?),
+            }
 
             dir.close()?
         }
         Cmd::Deps { v1, v2 } => {
-            let index = crates_index::GitIndex::new_cargo_default()?;
-            let krate = index
-                .crate_(&args.crate_name)
-                .ok_or(anyhow!("Couldn't find crate name {}", args.crate_name))?;
-            let v1 = krate
-                .versions()
-                .iter()
-                .find(|v| v.version() == v1)
-                .cloned()
-                .ok_or(anyhow!(
-                    "Couldn't find version {} for crate {}",
-                    v1,
-                    args.crate_name
-                ))?;
-            let v2 = krate
-                .versions()
-                .iter()
-                .find(|v| v.version() == v2)
-                .cloned()
-                .ok_or(anyhow!(
-                    "Couldn't find version {} for crate {}",
-                    v2,
-                    args.crate_name
-                ))?;
-            let v1_map = v1
-                .dependencies()
-                .iter()
-                .map(|d| (d.name(), d))
-                .collect::<BTreeMap<_, _>>();
-            let v2_map = v2
-                .dependencies()
-                .iter()
-                .map(|d| (d.name(), d))
-                .collect::<BTreeMap<_, _>>();
-            let v1_deps = v1
-                .dependencies()
-                .iter()
-                .map(|d| d.name())
-                .collect::<BTreeSet<_>>();
-            let v2_deps = v2
-                .dependencies()
-                .iter()
-                .map(|d| d.name())
-                .collect::<BTreeSet<_>>();
-            let added = v2_deps.difference(&v1_deps).collect::<HashSet<_>>();
-            let removed = v1_deps.difference(&v2_deps).collect::<HashSet<_>>();
-
-            let dir = tempfile::tempdir()?;
-            for dep in v1_deps.union(&v2_deps) {
-                if added.contains(dep) {
-                    println!(
-                        "{}",
-                        format!("+{:#?}", v2_map.get(dep).unwrap())
-                            .lines()
-                            .collect::<Vec<_>>()
-                            .join("\n+")
-                    );
-                } else if removed.contains(dep) {
-                    println!(
-                        "{}",
-                        format!("-{:#?}", v1_map.get(dep).unwrap())
-                            .lines()
-                            .collect::<Vec<_>>()
-                            .join("\n-")
-                    );
-                } else {
-                    std::fs::write(
-                        dir.as_ref()
-                            .join(format!("{}-{}", &args.crate_name, v1.version())),
-                        format!("{:#?}\n", v1_map.get(dep).unwrap()),
-                    )?;
-                    std::fs::write(
-                        dir.as_ref()
-                            .join(format!("{}-{}", &args.crate_name, v2.version())),
-                        format!("{:#?}\n", v2_map.get(dep).unwrap()),
-                    )?;
-                    let diff = Command::new("diff")
-                        .args([
-                            "-w",
-                            // "--color=always",
-                            "--unified=1000",
-                        ])
-                        .args([
-                            format!("{}-{}", args.crate_name, v1.version()),
-                            format!("{}-{}", args.crate_name, v2.version()),
-                        ])
-                        .current_dir(&dir)
-                        .output()?;
-                    if !diff.status.success() {
-                        println!(
-                            "{}",
-                            from_utf8(&diff.stdout)?
-                                .lines()
-                                .skip(3)
-                                .collect::<Vec<_>>()
-                                .join("\n")
-                        );
-                    }
-                }
+            let index = registry::Index::open(&registry_url)?;
+            let deps_diff = deps::deps_diff(&index, &args.crate_name, &v1, &v2)?;
+            match args.format {
+                Format::Text => print!("{}", deps_diff.to_text()),
+                Format::Json => println!("{}", serde_json::to_string_pretty(&deps_diff)?),
+            }
+        }
+        Cmd::TreeDiff { v1, v2 } => {
+            let tree_diff = tree::tree_diff(&registry_url, &args.crate_name, &v1, &v2)?;
+            match args.format {
+                Format::Text => print!("{}", tree_diff.to_text()),
+                Format::Json => println!("{}", serde_json::to_string_pretty(&tree_diff)?),
+            }
+        }
+        Cmd::Semver { v1, v2 } => {
+            let index = registry::Index::open(&registry_url)?;
+            let report = semver::semver_report(&index, &args.crate_name, &v1, &v2)?;
+            match args.format {
+                Format::Text => print!("{}", report.to_text()),
+                Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            }
+        }
+        Cmd::LintDiff { v1, v2 } => {
+            let index = registry::Index::open(&registry_url)?;
+            let report = lint::lint_diff(&index, &args.crate_name, &v1, &v2)?;
+            match args.format {
+                Format::Text => print!("{}", report.to_text()),
+                Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
             }
-            dir.close()?
         }
     }
     Ok(())
@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+/// The sparse crates.io index, used whenever `--registry` is not given and the user's cargo
+/// config doesn't override `[source.crates-io]`.
+const DEFAULT_SPARSE_URL: &str = "sparse+https://index.crates.io/";
+
+#[derive(Debug, Default, Deserialize)]
+struct CargoConfig {
+    #[serde(default)]
+    registries: BTreeMap<String, RegistryEntry>,
+    #[serde(default)]
+    source: BTreeMap<String, SourceEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistryEntry {
+    index: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SourceEntry {
+    registry: Option<String>,
+    #[serde(rename = "replace-with")]
+    replace_with: Option<String>,
+}
+
+/// `.cargo/config.toml` files that apply here, nearest first: walking up from the current
+/// directory, then `$CARGO_HOME`. Cargo itself merges all of these, with the nearest file's
+/// keys winning on conflicts, so callers should prefer the first definition of a given key.
+fn cargo_config_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Ok(mut dir) = std::env::current_dir() {
+        loop {
+            for name in [".cargo/config.toml", ".cargo/config"] {
+                let candidate = dir.join(name);
+                if candidate.is_file() {
+                    paths.push(candidate);
+                }
+            }
+            match dir.parent() {
+                Some(parent) => dir = parent.to_path_buf(),
+                None => break,
+            }
+        }
+    }
+    if let Ok(home) = home::cargo_home() {
+        for name in ["config.toml", "config"] {
+            let candidate = home.join(name);
+            if candidate.is_file() {
+                paths.push(candidate);
+            }
+        }
+    }
+    paths
+}
+
+/// Merges configs in the order `cargo_config_paths` returns them (nearest-first), keeping the
+/// first definition seen of any given key. Split out from `load_cargo_config` so the merge
+/// logic can be unit-tested without touching the filesystem.
+fn merge_configs(configs: impl IntoIterator<Item = CargoConfig>) -> CargoConfig {
+    let mut merged = CargoConfig::default();
+    for parsed in configs {
+        for (name, entry) in parsed.registries {
+            merged.registries.entry(name).or_insert(entry);
+        }
+        for (name, entry) in parsed.source {
+            merged.source.entry(name).or_insert(entry);
+        }
+    }
+    merged
+}
+
+fn load_cargo_config() -> Result<CargoConfig> {
+    let mut configs = Vec::new();
+    for path in cargo_config_paths() {
+        let text = std::fs::read_to_string(&path)?;
+        configs.push(toml::from_str(&text).map_err(|e| anyhow!("failed to parse {}: {e}", path.display()))?);
+    }
+    Ok(merge_configs(configs))
+}
+
+/// Follows a chain of `[source.<name>] replace-with = ...` entries to the registry URL they
+/// ultimately point at, the same way cargo resolves source replacement.
+fn resolve_source_url(config: &CargoConfig, mut name: String) -> Result<String> {
+    let mut seen = std::collections::BTreeSet::new();
+    loop {
+        if !seen.insert(name.clone()) {
+            return Err(anyhow!("`replace-with` cycle detected at `{name}`"));
+        }
+        let source = config
+            .source
+            .get(&name)
+            .ok_or_else(|| anyhow!("no `[source.{name}]` entry in cargo config"))?;
+        if let Some(replacement) = &source.replace_with {
+            name = replacement.clone();
+            continue;
+        }
+        return source
+            .registry
+            .clone()
+            .ok_or_else(|| anyhow!("`[source.{name}]` has neither `registry` nor `replace-with`"));
+    }
+}
+
+/// Resolves `--registry <name|url>` to an index URL, honoring `[registries]` and
+/// `[source]`/`replace-with` entries from cargo config the way `cargo` itself does. `None`
+/// resolves to the sparse crates.io index, unless `[source.crates-io]` has been redirected.
+pub fn resolve_registry_url(registry: Option<&str>) -> Result<String> {
+    match registry {
+        None => match resolve_source_url(&load_cargo_config()?, "crates-io".to_string()) {
+            Ok(url) => Ok(url),
+            Err(_) => Ok(DEFAULT_SPARSE_URL.to_string()),
+        },
+        Some(registry) if registry.contains("://") => Ok(registry.to_string()),
+        Some(name) => {
+            let config = load_cargo_config()?;
+            config
+                .registries
+                .get(name)
+                .ok_or_else(|| anyhow!("no `[registries.{name}]` entry found in cargo config"))?
+                .index
+                .clone()
+                .ok_or_else(|| anyhow!("`[registries.{name}]` has no `index`"))
+        }
+    }
+}
+
+/// A crates.io-style registry index, backed by either a local git checkout or the sparse HTTP
+/// protocol. Hides which one is in play from callers that just want to look up a crate.
+pub enum Index {
+    Git(Box<crates_index::GitIndex>),
+    Sparse {
+        index: crates_index::SparseIndex,
+        client: reqwest::blocking::Client,
+    },
+}
+
+impl Index {
+    pub fn open(url: &str) -> Result<Index> {
+        if url.starts_with("sparse+") {
+            Ok(Index::Sparse {
+                index: crates_index::SparseIndex::from_url(url)?,
+                client: reqwest::blocking::ClientBuilder::new().gzip(true).build()?,
+            })
+        } else {
+            let mut index = crates_index::GitIndex::from_url(url)?;
+            index.update()?;
+            Ok(Index::Git(Box::new(index)))
+        }
+    }
+
+    pub fn crate_(&self, name: &str) -> Result<crates_index::Crate> {
+        match self {
+            Index::Git(index) => index
+                .crate_(name)
+                .ok_or_else(|| anyhow!("couldn't find crate name {}", name)),
+            Index::Sparse { index, client } => {
+                let request: reqwest::blocking::Request =
+                    index.make_cache_request(name)?.body(Vec::new())?.try_into()?;
+                let response = client.execute(request)?;
+                let mut builder = http::Response::builder().status(response.status());
+                if let Some(headers) = builder.headers_mut() {
+                    headers.extend(response.headers().iter().map(|(k, v)| (k.clone(), v.clone())));
+                }
+                let response = builder.body(response.bytes()?.to_vec())?;
+                index
+                    .parse_cache_response(name, response, true)?
+                    .ok_or_else(|| anyhow!("couldn't find crate name {}", name))
+            }
+        }
+    }
+
+    fn index_config(&self) -> Result<crates_index::IndexConfig> {
+        match self {
+            Index::Git(index) => Ok(index.index_config()?),
+            Index::Sparse { index, client } => {
+                let request: reqwest::blocking::Request =
+                    index.make_config_request()?.body(Vec::new())?.try_into()?;
+                let response = client.execute(request)?;
+                let mut builder = http::Response::builder().status(response.status());
+                if let Some(headers) = builder.headers_mut() {
+                    headers.extend(response.headers().iter().map(|(k, v)| (k.clone(), v.clone())));
+                }
+                let response = builder.body(response.bytes()?.to_vec())?;
+                Ok(index.parse_config_response(response, true)?)
+            }
+        }
+    }
+
+    /// The URL this registry's `config.json`/`index_config` says a crate tarball lives at.
+    pub fn download_url(&self, name: &str, version: &str) -> Result<String> {
+        self.index_config()?
+            .download_url(name, version)
+            .ok_or_else(|| anyhow!("registry has no usable `dl` download template"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registries(entries: &[(&str, &str)]) -> BTreeMap<String, RegistryEntry> {
+        entries
+            .iter()
+            .map(|(name, index)| (name.to_string(), RegistryEntry { index: Some(index.to_string()) }))
+            .collect()
+    }
+
+    fn sources(entries: &[(&str, Option<&str>, Option<&str>)]) -> BTreeMap<String, SourceEntry> {
+        entries
+            .iter()
+            .map(|(name, registry, replace_with)| {
+                (
+                    name.to_string(),
+                    SourceEntry { registry: registry.map(str::to_string), replace_with: replace_with.map(str::to_string) },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merge_configs_prefers_the_nearest_file() {
+        let nearest =
+            CargoConfig { registries: registries(&[("foo", "https://nearest")]), source: BTreeMap::new() };
+        let farther = CargoConfig {
+            registries: registries(&[("foo", "https://farther"), ("bar", "https://bar")]),
+            source: BTreeMap::new(),
+        };
+
+        let merged = merge_configs([nearest, farther]);
+
+        assert_eq!(merged.registries["foo"].index.as_deref(), Some("https://nearest"));
+        assert_eq!(merged.registries["bar"].index.as_deref(), Some("https://bar"));
+    }
+
+    #[test]
+    fn resolve_source_url_follows_replace_with_chain() {
+        let config = CargoConfig {
+            registries: BTreeMap::new(),
+            source: sources(&[("crates-io", None, Some("mirror")), ("mirror", Some("mirror-registry"), None)]),
+        };
+
+        let url = resolve_source_url(&config, "crates-io".to_string()).unwrap();
+
+        assert_eq!(url, "mirror-registry");
+    }
+
+    #[test]
+    fn resolve_source_url_detects_a_cycle() {
+        let config = CargoConfig {
+            registries: BTreeMap::new(),
+            source: sources(&[("a", None, Some("b")), ("b", None, Some("a"))]),
+        };
+
+        assert!(resolve_source_url(&config, "a".to_string()).is_err());
+    }
+
+    #[test]
+    fn resolve_source_url_errors_without_registry_or_replace_with() {
+        let config = CargoConfig { registries: BTreeMap::new(), source: sources(&[("crates-io", None, None)]) };
+
+        assert!(resolve_source_url(&config, "crates-io".to_string()).is_err());
+    }
+}
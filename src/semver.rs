@@ -0,0 +1,153 @@
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use semver::Version;
+use serde::Serialize;
+
+use crate::deps;
+use crate::registry;
+
+/// The semver bump a version change represents, per Cargo's caret-compatibility rules rather
+/// than plain component comparison: a `0.x` release is far stricter than `^1.0` about what
+/// counts as compatible.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Bump {
+    Major,
+    Minor,
+    Patch,
+    Same,
+}
+
+impl fmt::Display for Bump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Bump::Major => "major",
+            Bump::Minor => "minor",
+            Bump::Patch => "patch",
+            Bump::Same => "same",
+        })
+    }
+}
+
+/// Classifies `old -> new` the way `^old` caret matching would: does `^old` already allow
+/// `new`, and if not, which component broke compatibility first.
+///
+/// Below `1.0.0`, caret matching is stricter than the raw major/minor/patch fields suggest:
+/// `^0.y.z` (`y > 0`) only matches the same minor version, and `^0.0.z` only matches that exact
+/// version. So a `0.y` minor bump, or any change at all within `0.0.z`, is classified as a
+/// breaking (`Major`) change even though the literal version field that changed is the minor or
+/// patch component.
+pub fn classify_bump(old: &Version, new: &Version) -> Bump {
+    if old == new {
+        return Bump::Same;
+    }
+    if old.major != new.major {
+        return Bump::Major;
+    }
+    if old.major == 0 {
+        if old.minor != new.minor || old.minor == 0 {
+            return Bump::Major;
+        }
+        return Bump::Patch;
+    }
+    if old.minor != new.minor {
+        return Bump::Minor;
+    }
+    Bump::Patch
+}
+
+/// Structured result of [`semver_report`], serializable for `--format json`.
+#[derive(Serialize)]
+pub struct SemverReport {
+    pub old_version: String,
+    pub new_version: String,
+    pub bump: Bump,
+    /// Dependency changes that look inconsistent with `bump`, e.g. a removed normal dependency
+    /// on a release classified as `Patch` or `Same`. This is a heuristic, not a proof: it can
+    /// both miss real breakage and flag changes that are actually fine.
+    pub warnings: Vec<String>,
+}
+
+impl SemverReport {
+    pub fn to_text(&self) -> String {
+        let mut output = format!("{} -> {}: {} bump\n", self.old_version, self.new_version, self.bump);
+        for warning in &self.warnings {
+            output.push_str(&format!("warning: {warning}\n"));
+        }
+        output
+    }
+}
+
+pub fn semver_report(index: &registry::Index, crate_name: &str, v1: &str, v2: &str) -> Result<SemverReport> {
+    let old = Version::parse(v1).map_err(|e| anyhow!("{v1:?} is not a valid semver version: {e}"))?;
+    let new = Version::parse(v2).map_err(|e| anyhow!("{v2:?} is not a valid semver version: {e}"))?;
+    let bump = classify_bump(&old, &new);
+
+    let mut warnings = Vec::new();
+    if matches!(bump, Bump::Patch | Bump::Same) {
+        let deps_diff = deps::deps_diff(index, crate_name, v1, v2)?;
+        for dep in &deps_diff.normal.removed {
+            warnings.push(format!("normal dependency `{}` was removed on a {bump} release", dep.name));
+        }
+        for dep in &deps_diff.normal.changed {
+            if dep.old_requirement != dep.new_requirement {
+                warnings.push(format!(
+                    "normal dependency `{}` requirement changed from {:?} to {:?} on a {bump} release",
+                    dep.name, dep.old_requirement, dep.new_requirement
+                ));
+            }
+        }
+    }
+
+    Ok(SemverReport { old_version: v1.to_string(), new_version: v2.to_string(), bump, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn identical_version_is_same() {
+        assert_eq!(classify_bump(&v("1.2.3"), &v("1.2.3")), Bump::Same);
+    }
+
+    #[test]
+    fn major_bump_at_1_0_and_above() {
+        assert_eq!(classify_bump(&v("1.2.3"), &v("2.0.0")), Bump::Major);
+    }
+
+    #[test]
+    fn minor_bump_at_1_0_and_above_is_compatible() {
+        assert_eq!(classify_bump(&v("1.2.3"), &v("1.3.0")), Bump::Minor);
+    }
+
+    #[test]
+    fn patch_bump_at_1_0_and_above_is_compatible() {
+        assert_eq!(classify_bump(&v("1.2.3"), &v("1.2.4")), Bump::Patch);
+    }
+
+    #[test]
+    fn zero_x_minor_bump_is_breaking() {
+        assert_eq!(classify_bump(&v("0.2.3"), &v("0.3.0")), Bump::Major);
+    }
+
+    #[test]
+    fn zero_x_patch_bump_is_compatible() {
+        assert_eq!(classify_bump(&v("0.2.3"), &v("0.2.4")), Bump::Patch);
+    }
+
+    #[test]
+    fn zero_zero_x_patch_bump_is_breaking() {
+        assert_eq!(classify_bump(&v("0.0.3"), &v("0.0.4")), Bump::Major);
+    }
+
+    #[test]
+    fn zero_to_one_major_is_breaking() {
+        assert_eq!(classify_bump(&v("0.9.0"), &v("1.0.0")), Bump::Major);
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::from_utf8;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::success_or_error;
+
+/// The `"id"` string cargo assigns a resolved package; unique per name+version+source.
+type PackageId = String;
+
+struct ResolvedPkg {
+    name: String,
+    version: String,
+    parents: BTreeSet<PackageId>,
+}
+
+/// Resolves the full transitive dependency graph for `crate_name = "=version"` by generating a
+/// throwaway manifest that depends on exactly that version and asking `cargo metadata` to do the
+/// resolution, rather than scraping `cargo tree` text output.
+fn resolve_graph(registry_url: &str, crate_name: &str, version: &str) -> Result<BTreeMap<PackageId, ResolvedPkg>> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(
+        dir.path().join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"crate-diff-tree-probe\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+             [dependencies]\n{crate_name} = {{ version = \"={version}\", registry = \"crate-diff-probe\" }}\n"
+        ),
+    )?;
+    // `[registries]` is only honored from cargo *config*, never from the manifest itself.
+    std::fs::create_dir(dir.path().join(".cargo"))?;
+    std::fs::write(
+        dir.path().join(".cargo").join("config.toml"),
+        format!("[registries.crate-diff-probe]\nindex = \"{registry_url}\"\n"),
+    )?;
+    std::fs::create_dir(dir.path().join("src"))?;
+    std::fs::write(dir.path().join("src").join("main.rs"), "fn main() {}\n")?;
+
+    // `--manifest-path` alone doesn't change where cargo looks for `.cargo/config.toml`; it
+    // still walks up from the process's current directory. Run from inside the probe dir so the
+    // `[registries]` entry above is actually picked up.
+    let mut cmd = std::process::Command::new("cargo");
+    cmd.args(["metadata", "--format-version=1"])
+        .current_dir(dir.path());
+    let output = success_or_error(&mut cmd)?;
+    let metadata: Value = serde_json::from_str(from_utf8(&output.stdout)?)?;
+
+    let packages = metadata["packages"]
+        .as_array()
+        .ok_or(anyhow!("`cargo metadata` output had no `packages` array"))?;
+    let mut names = BTreeMap::<PackageId, (String, String)>::new();
+    for pkg in packages {
+        let id = pkg["id"].as_str().ok_or(anyhow!("package with no id"))?;
+        let name = pkg["name"].as_str().ok_or(anyhow!("package with no name"))?;
+        let version = pkg["version"]
+            .as_str()
+            .ok_or(anyhow!("package with no version"))?;
+        names.insert(id.to_string(), (name.to_string(), version.to_string()));
+    }
+
+    let nodes = metadata["resolve"]["nodes"]
+        .as_array()
+        .ok_or(anyhow!("`cargo metadata` output had no `resolve.nodes`"))?;
+    let mut parents = BTreeMap::<PackageId, BTreeSet<PackageId>>::new();
+    for node in nodes {
+        let id = node["id"].as_str().ok_or(anyhow!("node with no id"))?.to_string();
+        for dep in node["dependencies"]
+            .as_array()
+            .ok_or(anyhow!("node with no dependencies array"))?
+        {
+            let dep_id = dep.as_str().ok_or(anyhow!("dependency id was not a string"))?;
+            parents
+                .entry(dep_id.to_string())
+                .or_default()
+                .insert(id.clone());
+        }
+    }
+
+    // The probe's own id embeds the random tempdir path it was generated in, so it's never equal
+    // between two `resolve_graph` calls; left in, it'd make the crate's top-level dependents look
+    // like they changed on every single diff. It isn't a real dependent of anything, so strip it
+    // out of every parents set rather than just excluding it from `graph` below.
+    let probe_id = names
+        .iter()
+        .find(|(_, (name, _))| name == "crate-diff-tree-probe")
+        .map(|(id, _)| id.clone());
+
+    let mut graph = BTreeMap::new();
+    for (id, (name, version)) in names {
+        // Drop the throwaway probe package itself; only its dependency graph is interesting.
+        if name == "crate-diff-tree-probe" {
+            continue;
+        }
+        let mut pkg_parents = parents.remove(&id).unwrap_or_default();
+        if let Some(probe_id) = &probe_id {
+            pkg_parents.remove(probe_id);
+        }
+        graph.insert(id.clone(), ResolvedPkg { name, version, parents: pkg_parents });
+    }
+    Ok(graph)
+}
+
+fn index_by_name(graph: &BTreeMap<PackageId, ResolvedPkg>) -> BTreeMap<&str, Vec<(&PackageId, &str)>> {
+    let mut index = BTreeMap::<&str, Vec<(&PackageId, &str)>>::new();
+    for (id, pkg) in graph {
+        index
+            .entry(pkg.name.as_str())
+            .or_default()
+            .push((id, pkg.version.as_str()));
+    }
+    index
+}
+
+/// One line of a [`TreeDiff`]: a version added/removed from the graph, or one whose set of
+/// direct dependents (parents) changed between the two resolutions.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TreeEntry {
+    Added { name: String, version: String },
+    Removed { name: String, version: String },
+    ParentsChanged { name: String, version: String, added_parents: Vec<String>, removed_parents: Vec<String> },
+}
+
+/// Structured result of [`tree_diff`], serializable for `--format json`.
+#[derive(Serialize)]
+pub struct TreeDiff {
+    pub entries: Vec<TreeEntry>,
+}
+
+impl TreeDiff {
+    pub fn to_text(&self) -> String {
+        let mut output = String::new();
+        for entry in &self.entries {
+            match entry {
+                TreeEntry::Added { name, version } => output.push_str(&format!("+{name} {version}\n")),
+                TreeEntry::Removed { name, version } => output.push_str(&format!("-{name} {version}\n")),
+                TreeEntry::ParentsChanged { name, version, added_parents, removed_parents } => {
+                    output.push_str(&format!("~{name} {version} (dependents changed)\n"));
+                    for parent in added_parents {
+                        output.push_str(&format!("  +{parent}\n"));
+                    }
+                    for parent in removed_parents {
+                        output.push_str(&format!("  -{parent}\n"));
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+pub fn tree_diff(registry_url: &str, crate_name: &str, v1: &str, v2: &str) -> Result<TreeDiff> {
+    let graph1 = resolve_graph(registry_url, crate_name, v1)?;
+    let graph2 = resolve_graph(registry_url, crate_name, v2)?;
+    let index1 = index_by_name(&graph1);
+    let index2 = index_by_name(&graph2);
+
+    let names = index1.keys().chain(index2.keys()).copied().collect::<BTreeSet<_>>();
+    let mut entries = Vec::new();
+    for name in names {
+        match (index1.get(name), index2.get(name)) {
+            (None, Some(added)) => {
+                for (_, version) in added {
+                    entries.push(TreeEntry::Added { name: name.to_string(), version: version.to_string() });
+                }
+            }
+            (Some(removed), None) => {
+                for (_, version) in removed {
+                    entries.push(TreeEntry::Removed { name: name.to_string(), version: version.to_string() });
+                }
+            }
+            (Some(e1), Some(e2)) => {
+                let versions1 = e1.iter().map(|(_, v)| *v).collect::<BTreeSet<_>>();
+                let versions2 = e2.iter().map(|(_, v)| *v).collect::<BTreeSet<_>>();
+                for version in versions1.difference(&versions2) {
+                    entries.push(TreeEntry::Removed { name: name.to_string(), version: version.to_string() });
+                }
+                for version in versions2.difference(&versions1) {
+                    entries.push(TreeEntry::Added { name: name.to_string(), version: version.to_string() });
+                }
+                // Diff parents for every version present on both sides, independent of whether
+                // the *whole* version set for this name matches; diamond deps can add/remove one
+                // coexisting version while leaving others in place.
+                for (id, version) in e1 {
+                    if !versions2.contains(version) {
+                        continue;
+                    }
+                    let pkg1 = &graph1[*id];
+                    let Some(pkg2) = graph2.get(*id) else {
+                        continue;
+                    };
+                    if pkg1.parents != pkg2.parents {
+                        entries.push(TreeEntry::ParentsChanged {
+                            name: name.to_string(),
+                            version: version.to_string(),
+                            added_parents: pkg2
+                                .parents
+                                .difference(&pkg1.parents)
+                                .map(|parent| graph2.get(parent).map_or(parent.clone(), |p| p.name.clone()))
+                                .collect(),
+                            removed_parents: pkg1
+                                .parents
+                                .difference(&pkg2.parents)
+                                .map(|parent| graph1.get(parent).map_or(parent.clone(), |p| p.name.clone()))
+                                .collect(),
+                        });
+                    }
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+    Ok(TreeDiff { entries })
+}